@@ -0,0 +1,20 @@
+use crate::cohort::cohort_models::CohortId;
+
+/// Errors surfaced by the feature-flags service, covering both request-facing
+/// failures and the internal failures that can occur while evaluating a flag.
+#[derive(Debug, Clone, thiserror::Error)]
+pub enum FlagError {
+    #[error("Database is unavailable, please try again later")]
+    DatabaseUnavailable,
+
+    #[error("Internal error: {0}")]
+    Internal(String),
+
+    #[error("Failed to parse cohort filters")]
+    CohortFiltersParsingError,
+
+    /// A cohort's dependency graph contains a cycle: `path` names the chain of
+    /// cohort ids that led back to an already-visited cohort, in traversal order.
+    #[error("Cohort dependency cycle detected: {path:?}")]
+    CohortDependencyCycle { path: Vec<CohortId> },
+}
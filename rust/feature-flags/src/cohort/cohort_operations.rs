@@ -1,13 +1,30 @@
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use tokio::sync::RwLock;
 use tracing::instrument;
 
-use crate::cohort::cohort_models::{Cohort, CohortId, CohortProperty, InnerCohortProperty};
+use crate::cohort::cohort_models::{
+    Cohort, CohortId, CohortProperty, CohortPropertyType, CohortValues, InnerCohortProperty,
+};
 use crate::{
-    api::errors::FlagError, client::database::Client as DatabaseClient,
-    properties::property_models::PropertyFilter,
+    api::errors::FlagError,
+    client::database::Client as DatabaseClient,
+    properties::property_models::{OperatorType, PropertyFilter},
 };
 
+/// Three-color marking used by the DFS in `Cohort::build_dependency_graph` to
+/// detect cycles in the cohort dependency graph.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum VisitState {
+    White,
+    Gray,
+    Black,
+}
+
 impl Cohort {
     /// Returns all cohorts for a given team
     #[instrument(skip_all)]
@@ -53,27 +70,249 @@ impl Cohort {
         Ok(cohorts)
     }
 
-    /// Parses the filters JSON into a CohortProperty structure
-    // TODO: this doesn't handle the deprecated "groups" field, see
-    // https://github.com/PostHog/posthog/blob/feat/dynamic-cohorts-rust/posthog/models/cohort/cohort.py#L114-L169
-    // I'll handle that in a separate PR.
+    /// Checks whether a person belongs to a static cohort, by looking up the
+    /// precomputed membership table rather than evaluating a filter tree.
+    ///
+    /// Static cohorts (`is_static`) have no filters to traverse: their
+    /// membership was computed once (e.g. from a CSV upload or a one-off
+    /// query) and persisted, so evaluation should short-circuit to this
+    /// lookup instead of calling `parse_filters`/`matches`.
+    #[instrument(skip_all)]
+    pub async fn is_person_in_static_cohort(
+        client: Arc<dyn DatabaseClient + Send + Sync>,
+        cohort_id: CohortId,
+        person_id: i64,
+    ) -> Result<bool, FlagError> {
+        let mut conn = client.get_connection().await.map_err(|e| {
+            tracing::error!("Failed to get database connection: {}", e);
+            FlagError::DatabaseUnavailable
+        })?;
+
+        let query = r#"
+            SELECT EXISTS (
+                SELECT 1
+                  FROM posthog_cohortpeople AS cp
+                 WHERE cp.cohort_id = $1
+                   AND cp.person_id = $2
+            )
+        "#;
+
+        let is_member: bool = sqlx::query_scalar(query)
+            .bind(cohort_id)
+            .bind(person_id)
+            .fetch_one(&mut *conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to check static cohort membership for cohort {}: {}",
+                    cohort_id,
+                    e
+                );
+                FlagError::Internal(format!("Database query error: {}", e))
+            })?;
+
+        Ok(is_member)
+    }
+
+    /// Batch variant of `is_person_in_static_cohort`: returns the subset of
+    /// `person_ids` that belong to the static cohort.
+    #[instrument(skip_all)]
+    pub async fn get_static_cohort_members(
+        client: Arc<dyn DatabaseClient + Send + Sync>,
+        cohort_id: CohortId,
+        person_ids: &[i64],
+    ) -> Result<HashSet<i64>, FlagError> {
+        let mut conn = client.get_connection().await.map_err(|e| {
+            tracing::error!("Failed to get database connection: {}", e);
+            FlagError::DatabaseUnavailable
+        })?;
+
+        let query = r#"
+            SELECT cp.person_id
+              FROM posthog_cohortpeople AS cp
+             WHERE cp.cohort_id = $1
+               AND cp.person_id = ANY($2)
+        "#;
+
+        let members: Vec<i64> = sqlx::query_scalar(query)
+            .bind(cohort_id)
+            .bind(person_ids)
+            .fetch_all(&mut *conn)
+            .await
+            .map_err(|e| {
+                tracing::error!(
+                    "Failed to batch-check static cohort membership for cohort {}: {}",
+                    cohort_id,
+                    e
+                );
+                FlagError::Internal(format!("Database query error: {}", e))
+            })?;
+
+        Ok(members.into_iter().collect())
+    }
+
+    /// Builds a dependency graph over a batch of cohorts and returns a topologically
+    /// sorted evaluation order, so that a cohort referencing other cohorts is always
+    /// ordered after the cohorts it depends on.
+    ///
+    /// Uses an explicit DFS with three-color (white/gray/black) marking: a back-edge
+    /// to a gray node means we've found a cycle, which we report as
+    /// `FlagError::CohortDependencyCycle` naming the offending chain.
+    #[instrument(skip_all)]
+    pub fn build_dependency_graph(cohorts: &[Cohort]) -> Result<Vec<CohortId>, FlagError> {
+        let cohorts_by_id: HashMap<CohortId, &Cohort> =
+            cohorts.iter().map(|cohort| (cohort.id, cohort)).collect();
+
+        let mut dependencies: HashMap<CohortId, HashSet<CohortId>> =
+            HashMap::with_capacity(cohorts.len());
+        for cohort in cohorts {
+            dependencies.insert(cohort.id, cohort.extract_dependencies()?);
+        }
+
+        let mut state: HashMap<CohortId, VisitState> = HashMap::with_capacity(cohorts.len());
+        let mut path: Vec<CohortId> = Vec::new();
+        let mut order: Vec<CohortId> = Vec::with_capacity(cohorts.len());
+
+        for cohort in cohorts {
+            if !matches!(state.get(&cohort.id), Some(VisitState::Black)) {
+                Self::visit_cohort(
+                    cohort.id,
+                    &cohorts_by_id,
+                    &dependencies,
+                    &mut state,
+                    &mut path,
+                    &mut order,
+                )?;
+            }
+        }
+
+        Ok(order)
+    }
+
+    /// Visits a single cohort as part of the DFS in `build_dependency_graph`,
+    /// pushing it onto `order` only after all of its dependencies have been visited.
+    fn visit_cohort(
+        id: CohortId,
+        cohorts_by_id: &HashMap<CohortId, &Cohort>,
+        dependencies: &HashMap<CohortId, HashSet<CohortId>>,
+        state: &mut HashMap<CohortId, VisitState>,
+        path: &mut Vec<CohortId>,
+        order: &mut Vec<CohortId>,
+    ) -> Result<(), FlagError> {
+        match state.get(&id) {
+            Some(VisitState::Black) => return Ok(()),
+            Some(VisitState::Gray) => {
+                let cycle_start = path.iter().position(|&visited| visited == id).unwrap_or(0);
+                let mut cycle_path = path[cycle_start..].to_vec();
+                cycle_path.push(id);
+                return Err(FlagError::CohortDependencyCycle { path: cycle_path });
+            }
+            Some(VisitState::White) | None => {}
+        }
+
+        state.insert(id, VisitState::Gray);
+        path.push(id);
+
+        if let Some(deps) = dependencies.get(&id) {
+            for &dep_id in deps {
+                // Dependencies outside this batch (e.g. a different project) are
+                // resolved independently, so we only traverse the ones we have.
+                if cohorts_by_id.contains_key(&dep_id) {
+                    Self::visit_cohort(dep_id, cohorts_by_id, dependencies, state, path, order)?;
+                }
+            }
+        }
+
+        path.pop();
+        state.insert(id, VisitState::Black);
+        order.push(id);
+
+        Ok(())
+    }
+
+    /// Parses the filters JSON into a CohortProperty structure.
+    ///
+    /// Cohorts created before the dynamic-cohorts migration store their
+    /// conditions in the legacy `groups` column instead of `filters`; when
+    /// `filters` is absent or empty we fall back to `parse_legacy_groups` so
+    /// callers get a uniform `Vec<PropertyFilter>` regardless of which schema
+    /// version produced the cohort.
     pub fn parse_filters(&self) -> Result<Vec<PropertyFilter>, FlagError> {
-        let filters = match &self.filters {
-            Some(filters) => filters,
-            None => return Ok(Vec::new()), // Return empty vec if no filters
-        };
+        let has_modern_filters = self
+            .filters
+            .as_ref()
+            .and_then(|filters| filters.get("properties"))
+            .and_then(|properties| properties.get("values"))
+            .and_then(|values| values.as_array())
+            .is_some_and(|values| !values.is_empty());
+
+        if !has_modern_filters {
+            return self.parse_legacy_groups();
+        }
 
         let cohort_property: CohortProperty =
-            serde_json::from_value(filters.to_owned()).map_err(|e| {
-                tracing::error!("Failed to parse filters for cohort {}: {}", self.id, e);
-                FlagError::CohortFiltersParsingError
-            })?;
+            serde_json::from_value(self.filters.clone().expect("checked by has_modern_filters"))
+                .map_err(|e| {
+                    tracing::error!("Failed to parse filters for cohort {}: {}", self.id, e);
+                    FlagError::CohortFiltersParsingError
+                })?;
 
         let mut props = cohort_property.properties.to_inner();
         props.retain(|f| !(f.key == "id" && f.prop_type == "cohort"));
         Ok(props)
     }
 
+    /// Translates the deprecated `groups` column into the modern `PropertyFilter`
+    /// shape, for cohorts created before the dynamic-cohorts migration.
+    ///
+    /// Each legacy group is an implicit AND of its `properties`; the groups
+    /// themselves combine with OR, so we wrap them in an `InnerCohortProperty`
+    /// of `OR`-of-`AND`s and reuse `to_inner` to flatten it, same as the
+    /// modern `filters` path.
+    fn parse_legacy_groups(&self) -> Result<Vec<PropertyFilter>, FlagError> {
+        let Some(groups) = self.groups.as_array() else {
+            return Ok(Vec::new());
+        };
+
+        let mut values = Vec::with_capacity(groups.len());
+        for group in groups {
+            let properties = group
+                .get("properties")
+                .and_then(|properties| properties.as_array())
+                .cloned()
+                .unwrap_or_default();
+
+            let mut filters = Vec::with_capacity(properties.len());
+            for property in properties {
+                let filter: PropertyFilter = serde_json::from_value(property).map_err(|e| {
+                    tracing::error!(
+                        "Failed to parse legacy group property for cohort {}: {}",
+                        self.id,
+                        e
+                    );
+                    FlagError::CohortFiltersParsingError
+                })?;
+                filters.push(filter);
+            }
+
+            if !filters.is_empty() {
+                values.push(CohortValues {
+                    prop_type: "AND".to_string(),
+                    values: filters,
+                });
+            }
+        }
+
+        let legacy_property = InnerCohortProperty {
+            prop_type: CohortPropertyType::OR,
+            values,
+        };
+
+        let mut props = legacy_property.to_inner();
+        props.retain(|f| !(f.key == "id" && f.prop_type == "cohort"));
+        Ok(props)
+    }
+
     /// Extracts dependent CohortIds from the cohort's filters
     pub fn extract_dependencies(&self) -> Result<HashSet<CohortId>, FlagError> {
         let filters = match &self.filters {
@@ -81,18 +320,19 @@ impl Cohort {
             None => return Ok(HashSet::new()), // Return empty set if no filters
         };
 
-        let cohort_property: CohortProperty =
-            serde_json::from_value(filters.clone()).map_err(|e| {
-                tracing::error!("Failed to parse filters for cohort {}: {}", self.id, e);
-                FlagError::CohortFiltersParsingError
-            })?;
-
+        // We walk the raw JSON rather than the typed `CohortProperty` tree because
+        // condition sets built in the UI can nest groups to arbitrary depth, which
+        // the typed structures (one level of `InnerCohortProperty` -> `CohortValues`)
+        // don't represent.
+        let properties = filters.get("properties").unwrap_or(filters);
         let mut dependencies = HashSet::new();
-        Self::traverse_filters(&cohort_property.properties, &mut dependencies)?;
+        Self::traverse_filters(properties, &mut dependencies)?;
         Ok(dependencies)
     }
 
-    /// Recursively traverses the filter tree to find cohort dependencies
+    /// Recursively traverses the filter tree to find cohort dependencies, at any
+    /// nesting depth, and accepts cohort references expressed either as a single
+    /// `CohortId` or as a JSON array of ids.
     ///
     /// Example filter tree structure:
     /// ```json
@@ -114,6 +354,12 @@ impl Cohort {
     ///             "value": "@posthog.com",
     ///             "type": "person",
     ///             "operator": "icontains"
+    ///           },
+    ///           {
+    ///             "type": "OR",
+    ///             "values": [
+    ///               { "key": "id", "value": [456, 789], "type": "cohort" }
+    ///             ]
     ///           }
     ///         ]
     ///       }
@@ -122,24 +368,131 @@ impl Cohort {
     /// }
     /// ```
     fn traverse_filters(
-        inner: &InnerCohortProperty,
+        node: &serde_json::Value,
         dependencies: &mut HashSet<CohortId>,
     ) -> Result<(), FlagError> {
-        for cohort_values in &inner.values {
-            for filter in &cohort_values.values {
-                if filter.is_cohort() {
-                    // Assuming the value is a single integer CohortId
-                    if let Some(cohort_id) = filter.value.as_i64() {
-                        dependencies.insert(cohort_id as CohortId);
-                    } else {
-                        return Err(FlagError::CohortFiltersParsingError);
+        match node {
+            serde_json::Value::Object(fields) => {
+                if fields.get("type").and_then(|t| t.as_str()) == Some("cohort") {
+                    match fields.get("value") {
+                        Some(serde_json::Value::Array(ids)) => {
+                            for id in ids {
+                                let cohort_id =
+                                    id.as_i64().ok_or(FlagError::CohortFiltersParsingError)?;
+                                dependencies.insert(cohort_id as CohortId);
+                            }
+                        }
+                        Some(value) => {
+                            let cohort_id =
+                                value.as_i64().ok_or(FlagError::CohortFiltersParsingError)?;
+                            dependencies.insert(cohort_id as CohortId);
+                        }
+                        None => return Err(FlagError::CohortFiltersParsingError),
                     }
                 }
-                // NB: we don't support nested cohort properties, so we don't need to traverse further
+
+                if let Some(values) = fields.get("values") {
+                    Self::traverse_filters(values, dependencies)?;
+                }
+            }
+            serde_json::Value::Array(items) => {
+                for item in items {
+                    Self::traverse_filters(item, dependencies)?;
+                }
             }
+            _ => {}
         }
         Ok(())
     }
+
+    /// Evaluates whether a person, described by `props`, matches this
+    /// cohort's filter tree, to arbitrary nesting depth.
+    ///
+    /// Walks the raw `filters` JSON rather than the typed `CohortProperty`
+    /// structures (mirroring `traverse_filters`), since condition sets built
+    /// in the UI can nest groups deeper than `InnerCohortProperty`/
+    /// `CohortValues` can represent. Dynamic cohorts with no filters match
+    /// vacuously. Static cohorts are not handled here — see
+    /// `evaluate_membership`, which short-circuits to the precomputed
+    /// membership table instead of traversing filters.
+    pub fn matches(
+        &self,
+        props: &HashMap<String, serde_json::Value>,
+        resolver: &impl CohortResolver,
+    ) -> Result<bool, FlagError> {
+        let Some(filters) = &self.filters else {
+            return Ok(true);
+        };
+        let properties = filters.get("properties").unwrap_or(filters);
+        matches_node(properties, props, resolver)
+    }
+
+    /// Evaluates whether `person_id` belongs to this cohort, choosing the
+    /// right evaluation strategy for the cohort's kind: static cohorts
+    /// short-circuit to the precomputed membership table instead of
+    /// traversing property filters, since they have no filter tree to
+    /// evaluate.
+    #[instrument(skip_all)]
+    pub async fn evaluate_membership(
+        &self,
+        client: Arc<dyn DatabaseClient + Send + Sync>,
+        person_id: i64,
+        props: &HashMap<String, serde_json::Value>,
+        resolver: &impl CohortResolver,
+    ) -> Result<bool, FlagError> {
+        if self.is_static {
+            return Self::is_person_in_static_cohort(client, self.id, person_id).await;
+        }
+
+        self.matches(props, resolver)
+    }
+}
+
+/// Recursively evaluates a raw cohort-filter JSON node (the `properties`
+/// subtree of a cohort's `filters`) to arbitrary nesting depth, combining
+/// child results with the node's `type` (`"AND"`/`"OR"`) and resolving
+/// `"cohort"`-typed leaves via `resolver`. This is the JSON-tree counterpart
+/// to `InnerCohortProperty::matches`, used where filters may nest deeper than
+/// the typed structures can represent.
+fn matches_node(
+    node: &serde_json::Value,
+    props: &HashMap<String, serde_json::Value>,
+    resolver: &impl CohortResolver,
+) -> Result<bool, FlagError> {
+    let is_or = node
+        .get("type")
+        .and_then(|t| t.as_str())
+        .is_some_and(|t| t.eq_ignore_ascii_case("OR"));
+
+    let values = node
+        .get("values")
+        .and_then(|v| v.as_array())
+        .ok_or(FlagError::CohortFiltersParsingError)?;
+
+    let child_results = values
+        .iter()
+        .map(|child| {
+            if child.get("values").and_then(|v| v.as_array()).is_some() {
+                matches_node(child, props, resolver)
+            } else {
+                let filter: PropertyFilter =
+                    serde_json::from_value(child.clone()).map_err(|e| {
+                        tracing::error!("Failed to parse cohort filter leaf: {}", e);
+                        FlagError::CohortFiltersParsingError
+                    })?;
+                InnerCohortProperty::matches_filter(&filter, props, resolver)
+            }
+        })
+        .collect::<Result<Vec<bool>, FlagError>>()?;
+
+    // Groups are only ever combined with OR or AND; anything else (e.g. the
+    // "property"/"cohort" leaf-group markers) behaves as a conjunction,
+    // matching the single-element case these markers are used for.
+    Ok(if is_or {
+        child_results.into_iter().any(|result| result)
+    } else {
+        child_results.into_iter().all(|result| result)
+    })
 }
 
 impl InnerCohortProperty {
@@ -176,17 +529,407 @@ impl InnerCohortProperty {
             .flat_map(|value| value.values)
             .collect()
     }
+
+    /// Evaluates whether a person, described by `props`, matches this
+    /// already-parsed two-level cohort property tree (`InnerCohortProperty`'s
+    /// `values: Vec<CohortValues>`, each holding a flat `Vec<PropertyFilter>`).
+    ///
+    /// Combines each group's filter results with the group's boolean
+    /// operator, and flips the result of any leaf whose `negation` flag is
+    /// set. Leaves that reference another cohort (`PropertyFilter::is_cohort`)
+    /// are resolved by calling back into `resolver`, so nested cohort
+    /// references evaluate correctly.
+    ///
+    /// This only walks the two levels these types can represent. Condition
+    /// sets nested deeper than that (which `Cohort::traverse_filters` can
+    /// still extract dependencies from) aren't representable as a typed
+    /// `InnerCohortProperty` in the first place — use `Cohort::matches` for a
+    /// cohort's raw filters, which evaluates to arbitrary depth.
+    pub fn matches(
+        &self,
+        props: &HashMap<String, serde_json::Value>,
+        resolver: &impl CohortResolver,
+    ) -> Result<bool, FlagError> {
+        let group_results = self
+            .values
+            .iter()
+            .map(|group| Self::matches_group(group, props, resolver))
+            .collect::<Result<Vec<bool>, FlagError>>()?;
+
+        Ok(match self.prop_type {
+            CohortPropertyType::AND => group_results.into_iter().all(|result| result),
+            CohortPropertyType::OR => group_results.into_iter().any(|result| result),
+        })
+    }
+
+    fn matches_group(
+        group: &CohortValues,
+        props: &HashMap<String, serde_json::Value>,
+        resolver: &impl CohortResolver,
+    ) -> Result<bool, FlagError> {
+        let filter_results = group
+            .values
+            .iter()
+            .map(|filter| Self::matches_filter(filter, props, resolver))
+            .collect::<Result<Vec<bool>, FlagError>>()?;
+
+        Ok(if group.prop_type.eq_ignore_ascii_case("OR") {
+            filter_results.into_iter().any(|result| result)
+        } else {
+            filter_results.into_iter().all(|result| result)
+        })
+    }
+
+    fn matches_filter(
+        filter: &PropertyFilter,
+        props: &HashMap<String, serde_json::Value>,
+        resolver: &impl CohortResolver,
+    ) -> Result<bool, FlagError> {
+        let result = if filter.is_cohort() {
+            // A cohort leaf's value may be a single CohortId or a JSON array of
+            // them (see `Cohort::traverse_filters`); a person matches if they
+            // belong to any of the referenced cohorts.
+            match &filter.value {
+                serde_json::Value::Array(ids) => {
+                    let mut matched = false;
+                    for id in ids {
+                        let cohort_id =
+                            id.as_i64().ok_or(FlagError::CohortFiltersParsingError)? as CohortId;
+                        if resolver.resolve_cohort(cohort_id, props)? {
+                            matched = true;
+                        }
+                    }
+                    matched
+                }
+                value => {
+                    let cohort_id =
+                        value.as_i64().ok_or(FlagError::CohortFiltersParsingError)? as CohortId;
+                    resolver.resolve_cohort(cohort_id, props)?
+                }
+            }
+        } else {
+            Self::matches_property(filter, props)?
+        };
+
+        Ok(if filter.negation.unwrap_or(false) {
+            !result
+        } else {
+            result
+        })
+    }
+
+    fn matches_property(
+        filter: &PropertyFilter,
+        props: &HashMap<String, serde_json::Value>,
+    ) -> Result<bool, FlagError> {
+        let operator = filter.operator.unwrap_or(OperatorType::Exact);
+        let prop_value = props.get(&filter.key);
+
+        let result = match operator {
+            OperatorType::IsSet => prop_value.is_some(),
+            OperatorType::IsNotSet => prop_value.is_none(),
+            OperatorType::Exact | OperatorType::IsNot => {
+                let Some(prop_value) = prop_value else {
+                    return Ok(false);
+                };
+                let matched = match &filter.value {
+                    serde_json::Value::Array(values) => values.contains(prop_value),
+                    value => value == prop_value,
+                };
+                if operator == OperatorType::IsNot {
+                    !matched
+                } else {
+                    matched
+                }
+            }
+            OperatorType::IContains | OperatorType::NotIContains => {
+                let Some(prop_value) = prop_value.and_then(|v| v.as_str()) else {
+                    return Ok(false);
+                };
+                let Some(needle) = filter.value.as_str() else {
+                    return Ok(false);
+                };
+                let contains = prop_value.to_lowercase().contains(&needle.to_lowercase());
+                if operator == OperatorType::NotIContains {
+                    !contains
+                } else {
+                    contains
+                }
+            }
+            OperatorType::Regex | OperatorType::NotRegex => {
+                let Some(prop_value) = prop_value.and_then(|v| v.as_str()) else {
+                    return Ok(false);
+                };
+                let Some(pattern) = filter.value.as_str() else {
+                    return Ok(false);
+                };
+                let re = regex::Regex::new(pattern).map_err(|e| {
+                    tracing::error!("Invalid regex in cohort filter: {}", e);
+                    FlagError::CohortFiltersParsingError
+                })?;
+                let is_match = re.is_match(prop_value);
+                if operator == OperatorType::NotRegex {
+                    !is_match
+                } else {
+                    is_match
+                }
+            }
+            OperatorType::Gt | OperatorType::Gte | OperatorType::Lt | OperatorType::Lte => {
+                let Some(prop_value) = prop_value.and_then(Self::value_as_f64) else {
+                    return Ok(false);
+                };
+                let Some(filter_value) = Self::value_as_f64(&filter.value) else {
+                    return Ok(false);
+                };
+                match operator {
+                    OperatorType::Gt => prop_value > filter_value,
+                    OperatorType::Gte => prop_value >= filter_value,
+                    OperatorType::Lt => prop_value < filter_value,
+                    OperatorType::Lte => prop_value <= filter_value,
+                    _ => unreachable!(),
+                }
+            }
+            OperatorType::IsDateBefore | OperatorType::IsDateAfter => {
+                let Some(prop_value) = prop_value.and_then(Self::value_as_datetime) else {
+                    return Ok(false);
+                };
+                let Some(filter_value) = Self::value_as_datetime(&filter.value) else {
+                    return Ok(false);
+                };
+                match operator {
+                    OperatorType::IsDateBefore => prop_value < filter_value,
+                    OperatorType::IsDateAfter => prop_value > filter_value,
+                    _ => unreachable!(),
+                }
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn value_as_f64(value: &serde_json::Value) -> Option<f64> {
+        value
+            .as_f64()
+            .or_else(|| value.as_str().and_then(|s| s.parse::<f64>().ok()))
+    }
+
+    /// Parses a date/time value for `is_date_before`/`is_date_after`.
+    ///
+    /// Accepts, in order: an epoch (seconds) as a JSON number, an RFC3339
+    /// timestamp with an offset, a naive `YYYY-MM-DDTHH:MM:SS` timestamp
+    /// (assumed UTC), a bare `YYYY-MM-DD` date (midnight UTC), and finally an
+    /// epoch (seconds) written as a string — PostHog date properties are
+    /// commonly stored without an explicit offset.
+    fn value_as_datetime(value: &serde_json::Value) -> Option<DateTime<Utc>> {
+        if let Some(epoch) = value.as_i64() {
+            return Utc.timestamp_opt(epoch, 0).single();
+        }
+        let raw = value.as_str()?;
+
+        if let Ok(dt) = DateTime::parse_from_rfc3339(raw) {
+            return Some(dt.with_timezone(&Utc));
+        }
+        if let Ok(naive) = NaiveDateTime::parse_from_str(raw, "%Y-%m-%dT%H:%M:%S%.f") {
+            return Some(Utc.from_utc_datetime(&naive));
+        }
+        if let Ok(date) = NaiveDate::parse_from_str(raw, "%Y-%m-%d") {
+            return Some(Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0)?));
+        }
+        raw.parse::<i64>()
+            .ok()
+            .and_then(|epoch| Utc.timestamp_opt(epoch, 0).single())
+    }
+}
+
+/// Resolves whether a person matches a referenced cohort by id, allowing
+/// `InnerCohortProperty::matches` to evaluate nested cohort dependencies
+/// without needing to know how those cohorts are looked up or cached.
+pub trait CohortResolver {
+    fn resolve_cohort(
+        &self,
+        cohort_id: CohortId,
+        props: &HashMap<String, serde_json::Value>,
+    ) -> Result<bool, FlagError>;
+}
+
+/// Decouples cohort storage from the `Cohort` data type, following the
+/// repository-abstraction pattern used for `DatabaseClient` elsewhere. Callers
+/// should depend on `Arc<dyn CohortStore>` rather than issuing SQL directly,
+/// so that evaluation paths can be tested against a fake store and so a
+/// caching decorator like `CachingCohortStore` can be layered in transparently.
+#[async_trait]
+pub trait CohortStore: Send + Sync {
+    async fn list_from_pg(&self, project_id: i64) -> Result<Vec<Cohort>, FlagError>;
+
+    async fn is_person_in_static_cohort(
+        &self,
+        cohort_id: CohortId,
+        person_id: i64,
+    ) -> Result<bool, FlagError>;
+
+    async fn get_static_cohort_members(
+        &self,
+        cohort_id: CohortId,
+        person_ids: &[i64],
+    ) -> Result<HashSet<i64>, FlagError>;
+}
+
+/// The default `CohortStore` implementation, backed directly by Postgres.
+pub struct PgCohortStore {
+    client: Arc<dyn DatabaseClient + Send + Sync>,
+}
+
+impl PgCohortStore {
+    pub fn new(client: Arc<dyn DatabaseClient + Send + Sync>) -> Self {
+        Self { client }
+    }
+}
+
+#[async_trait]
+impl CohortStore for PgCohortStore {
+    async fn list_from_pg(&self, project_id: i64) -> Result<Vec<Cohort>, FlagError> {
+        Cohort::list_from_pg(self.client.clone(), project_id).await
+    }
+
+    async fn is_person_in_static_cohort(
+        &self,
+        cohort_id: CohortId,
+        person_id: i64,
+    ) -> Result<bool, FlagError> {
+        Cohort::is_person_in_static_cohort(self.client.clone(), cohort_id, person_id).await
+    }
+
+    async fn get_static_cohort_members(
+        &self,
+        cohort_id: CohortId,
+        person_ids: &[i64],
+    ) -> Result<HashSet<i64>, FlagError> {
+        Cohort::get_static_cohort_members(self.client.clone(), cohort_id, person_ids).await
+    }
+}
+
+/// A project's cached cohort list.
+struct CachedProjectCohorts {
+    cohorts: Vec<Cohort>,
+    cached_at: Instant,
+}
+
+/// A project's cached dependency-sorted evaluation order, kept separately from
+/// `CachedProjectCohorts` so that a cyclic cohort (which makes
+/// `Cohort::build_dependency_graph` fail) only affects callers that actually
+/// asked for the evaluation order, not the plain `list_from_pg` path.
+struct CachedDependencyOrder {
+    order: Result<Vec<CohortId>, FlagError>,
+    cached_at: Instant,
+}
+
+/// A `CohortStore` decorator that memoizes per-project cohort lists for `ttl`,
+/// so repeated flag evaluations within a request don't re-hit Postgres.
+/// Dependency graphs are computed and cached lazily, only when
+/// `list_with_dependency_order` is explicitly called, so that `list_from_pg`
+/// keeps the same contract as `PgCohortStore::list_from_pg` — it never fails
+/// because of a cyclic cohort elsewhere in the project. Static membership
+/// lookups are passed straight through, since they're already a single
+/// indexed row lookup.
+pub struct CachingCohortStore {
+    inner: Arc<dyn CohortStore>,
+    ttl: Duration,
+    cohorts_cache: RwLock<HashMap<i64, CachedProjectCohorts>>,
+    dependency_cache: RwLock<HashMap<i64, CachedDependencyOrder>>,
+}
+
+impl CachingCohortStore {
+    pub fn new(inner: Arc<dyn CohortStore>, ttl: Duration) -> Self {
+        Self {
+            inner,
+            ttl,
+            cohorts_cache: RwLock::new(HashMap::new()),
+            dependency_cache: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn cached_cohorts(&self, project_id: i64) -> Result<Vec<Cohort>, FlagError> {
+        if let Some(entry) = self.cohorts_cache.read().await.get(&project_id) {
+            if entry.cached_at.elapsed() < self.ttl {
+                return Ok(entry.cohorts.clone());
+            }
+        }
+
+        let cohorts = self.inner.list_from_pg(project_id).await?;
+
+        self.cohorts_cache.write().await.insert(
+            project_id,
+            CachedProjectCohorts {
+                cohorts: cohorts.clone(),
+                cached_at: Instant::now(),
+            },
+        );
+
+        Ok(cohorts)
+    }
+
+    /// Returns the cohorts for `project_id` along with their dependency-sorted
+    /// evaluation order, computing and caching the graph only on this call
+    /// (not as a side effect of a plain `list_from_pg`).
+    pub async fn list_with_dependency_order(
+        &self,
+        project_id: i64,
+    ) -> Result<(Vec<Cohort>, Vec<CohortId>), FlagError> {
+        let cohorts = self.cached_cohorts(project_id).await?;
+
+        if let Some(entry) = self.dependency_cache.read().await.get(&project_id) {
+            if entry.cached_at.elapsed() < self.ttl {
+                return entry.order.clone().map(|order| (cohorts, order));
+            }
+        }
+
+        let order = Cohort::build_dependency_graph(&cohorts);
+
+        self.dependency_cache.write().await.insert(
+            project_id,
+            CachedDependencyOrder {
+                order: order.clone(),
+                cached_at: Instant::now(),
+            },
+        );
+
+        order.map(|order| (cohorts, order))
+    }
+}
+
+#[async_trait]
+impl CohortStore for CachingCohortStore {
+    async fn list_from_pg(&self, project_id: i64) -> Result<Vec<Cohort>, FlagError> {
+        self.cached_cohorts(project_id).await
+    }
+
+    async fn is_person_in_static_cohort(
+        &self,
+        cohort_id: CohortId,
+        person_id: i64,
+    ) -> Result<bool, FlagError> {
+        self.inner
+            .is_person_in_static_cohort(cohort_id, person_id)
+            .await
+    }
+
+    async fn get_static_cohort_members(
+        &self,
+        cohort_id: CohortId,
+        person_ids: &[i64],
+    ) -> Result<HashSet<i64>, FlagError> {
+        self.inner
+            .get_static_cohort_members(cohort_id, person_ids)
+            .await
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{
-        cohort::cohort_models::{CohortPropertyType, CohortValues},
-        utils::test_utils::{
-            insert_cohort_for_team_in_pg, insert_new_team_in_pg, setup_pg_reader_client,
-            setup_pg_writer_client,
-        },
+    use crate::utils::test_utils::{
+        insert_cohort_for_team_in_pg, insert_new_team_in_pg, setup_pg_reader_client,
+        setup_pg_writer_client,
     };
     use serde_json::json;
 
@@ -230,6 +973,98 @@ mod tests {
         assert!(names.contains("Cohort 2"));
     }
 
+    #[tokio::test]
+    async fn test_is_person_in_static_cohort() {
+        let reader = setup_pg_reader_client(None).await;
+        let writer = setup_pg_writer_client(None).await;
+
+        let team = insert_new_team_in_pg(reader.clone(), None)
+            .await
+            .expect("Failed to insert team");
+
+        let static_cohort = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Static Cohort".to_string()),
+            json!({}),
+            true,
+        )
+        .await
+        .expect("Failed to insert static cohort");
+
+        let member_person_id = 1;
+        let other_person_id = 2;
+
+        let mut conn = writer
+            .get_connection()
+            .await
+            .expect("Failed to get connection");
+        sqlx::query("INSERT INTO posthog_cohortpeople (cohort_id, person_id) VALUES ($1, $2)")
+            .bind(static_cohort.id)
+            .bind(member_person_id)
+            .execute(&mut *conn)
+            .await
+            .expect("Failed to insert static cohort membership");
+        drop(conn);
+
+        assert!(Cohort::is_person_in_static_cohort(
+            reader.clone(),
+            static_cohort.id,
+            member_person_id
+        )
+        .await
+        .expect("Failed to check static cohort membership"));
+
+        assert!(!Cohort::is_person_in_static_cohort(
+            reader.clone(),
+            static_cohort.id,
+            other_person_id
+        )
+        .await
+        .expect("Failed to check static cohort membership"));
+    }
+
+    #[tokio::test]
+    async fn test_get_static_cohort_members() {
+        let reader = setup_pg_reader_client(None).await;
+        let writer = setup_pg_writer_client(None).await;
+
+        let team = insert_new_team_in_pg(reader.clone(), None)
+            .await
+            .expect("Failed to insert team");
+
+        let static_cohort = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Static Cohort".to_string()),
+            json!({}),
+            true,
+        )
+        .await
+        .expect("Failed to insert static cohort");
+
+        let mut conn = writer
+            .get_connection()
+            .await
+            .expect("Failed to get connection");
+        for person_id in [1, 2] {
+            sqlx::query("INSERT INTO posthog_cohortpeople (cohort_id, person_id) VALUES ($1, $2)")
+                .bind(static_cohort.id)
+                .bind(person_id)
+                .execute(&mut *conn)
+                .await
+                .expect("Failed to insert static cohort membership");
+        }
+        drop(conn);
+
+        let members =
+            Cohort::get_static_cohort_members(reader.clone(), static_cohort.id, &[1, 2, 3])
+                .await
+                .expect("Failed to fetch static cohort members");
+
+        assert_eq!(members, [1, 2].into_iter().collect::<HashSet<i64>>());
+    }
+
     #[test]
     fn test_cohort_parse_filters() {
         let cohort = Cohort {
@@ -260,12 +1095,73 @@ mod tests {
     }
 
     #[test]
-    fn test_cohort_property_to_inner() {
-        let cohort_property = InnerCohortProperty {
-            prop_type: CohortPropertyType::AND,
-            values: vec![CohortValues {
-                prop_type: "property".to_string(),
-                values: vec![
+    fn test_cohort_parse_filters_legacy_groups() {
+        let cohort = Cohort {
+            id: 1,
+            name: Some("Legacy Cohort".to_string()),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: None,
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!([
+                {
+                    "properties": [
+                        {"key": "email", "type": "person", "value": "@posthog.com", "operator": "icontains"}
+                    ]
+                },
+                {
+                    "properties": [
+                        {"key": "age", "type": "person", "value": 25, "operator": "gt"}
+                    ]
+                }
+            ]),
+            created_by_id: None,
+        };
+
+        let result = cohort.parse_filters().unwrap();
+        assert_eq!(result.len(), 2);
+        assert!(result.iter().any(|f| f.key == "email"));
+        assert!(result.iter().any(|f| f.key == "age"));
+    }
+
+    #[test]
+    fn test_cohort_parse_filters_empty_filters_and_groups() {
+        let cohort = Cohort {
+            id: 1,
+            name: Some("Empty Cohort".to_string()),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: Some(json!({})),
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!({}),
+            created_by_id: None,
+        };
+
+        let result = cohort.parse_filters().unwrap();
+        assert!(result.is_empty());
+    }
+
+    #[test]
+    fn test_cohort_property_to_inner() {
+        let cohort_property = InnerCohortProperty {
+            prop_type: CohortPropertyType::AND,
+            values: vec![CohortValues {
+                prop_type: "property".to_string(),
+                values: vec![
                     PropertyFilter {
                         key: "email".to_string(),
                         value: json!("test@example.com"),
@@ -340,4 +1236,550 @@ mod tests {
 
         assert_eq!(dependencies, expected_dependencies);
     }
+
+    #[test]
+    fn test_extract_dependencies_array_value() {
+        let cohort = Cohort {
+            id: 1,
+            name: Some("Test Cohort".to_string()),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: Some(
+                json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": [123, 456], "negation": false}]}]}}),
+            ),
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!({}),
+            created_by_id: None,
+        };
+
+        let dependencies = cohort.extract_dependencies().unwrap();
+        let expected: HashSet<CohortId> = [123, 456].iter().cloned().collect();
+        assert_eq!(dependencies, expected);
+    }
+
+    #[test]
+    fn test_extract_dependencies_arbitrarily_nested_groups() {
+        let cohort = Cohort {
+            id: 1,
+            name: Some("Test Cohort".to_string()),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: Some(json!({
+                "properties": {
+                    "type": "OR",
+                    "values": [
+                        {
+                            "type": "AND",
+                            "values": [
+                                {
+                                    "type": "OR",
+                                    "values": [
+                                        {"key": "id", "type": "cohort", "value": 999}
+                                    ]
+                                },
+                                {"key": "email", "type": "person", "value": "@posthog.com", "operator": "icontains"}
+                            ]
+                        }
+                    ]
+                }
+            })),
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!({}),
+            created_by_id: None,
+        };
+
+        let dependencies = cohort.extract_dependencies().unwrap();
+        let expected: HashSet<CohortId> = [999].iter().cloned().collect();
+        assert_eq!(dependencies, expected);
+    }
+
+    #[tokio::test]
+    async fn test_build_dependency_graph_orders_dependencies_first() {
+        let reader = setup_pg_reader_client(None).await;
+        let writer = setup_pg_writer_client(None).await;
+
+        let team = insert_new_team_in_pg(reader.clone(), None)
+            .await
+            .expect("Failed to insert team");
+
+        let base_cohort = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Base Cohort".to_string()),
+            json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "$browser", "type": "person", "value": ["Safari"], "negation": false, "operator": "exact"}]}]}}),
+            false,
+        )
+        .await
+        .expect("Failed to insert base_cohort");
+
+        let dependent_cohort = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Dependent Cohort".to_string()),
+            json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": base_cohort.id, "negation": false}]}]}}),
+            false,
+        )
+        .await
+        .expect("Failed to insert dependent_cohort");
+
+        let cohorts = Cohort::list_from_pg(reader, team.project_id)
+            .await
+            .expect("Failed to fetch cohorts");
+
+        let order = Cohort::build_dependency_graph(&cohorts).expect("Failed to build graph");
+
+        let base_pos = order
+            .iter()
+            .position(|&id| id == base_cohort.id)
+            .expect("base cohort missing from order");
+        let dependent_pos = order
+            .iter()
+            .position(|&id| id == dependent_cohort.id)
+            .expect("dependent cohort missing from order");
+
+        assert!(base_pos < dependent_pos);
+    }
+
+    #[tokio::test]
+    async fn test_build_dependency_graph_detects_cycle() {
+        let reader = setup_pg_reader_client(None).await;
+        let writer = setup_pg_writer_client(None).await;
+
+        let team = insert_new_team_in_pg(reader.clone(), None)
+            .await
+            .expect("Failed to insert team");
+
+        // Cohort A and B reference each other, forming a cycle. We insert A
+        // as a placeholder first so its row id exists, then insert B pointing
+        // at A; A's reference to B is patched in after fetching.
+        let cohort_a = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Cohort A".to_string()),
+            json!({"properties": {"type": "OR", "values": []}}),
+            false,
+        )
+        .await
+        .expect("Failed to insert cohort_a");
+
+        let cohort_b = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Cohort B".to_string()),
+            json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": cohort_a.id, "negation": false}]}]}}),
+            false,
+        )
+        .await
+        .expect("Failed to insert cohort_b");
+
+        let mut cohorts = Cohort::list_from_pg(reader.clone(), team.project_id)
+            .await
+            .expect("Failed to fetch cohorts");
+
+        // Simulate cohort_a referencing cohort_b, completing the cycle, without a
+        // second round-trip to Postgres.
+        for cohort in cohorts.iter_mut() {
+            if cohort.id == cohort_a.id {
+                cohort.filters = Some(
+                    json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": cohort_b.id, "negation": false}]}]}}),
+                );
+            }
+        }
+
+        let result = Cohort::build_dependency_graph(&cohorts);
+
+        match result {
+            Err(FlagError::CohortDependencyCycle { path }) => {
+                assert!(path.contains(&cohort_a.id));
+                assert!(path.contains(&cohort_b.id));
+            }
+            other => panic!("Expected CohortDependencyCycle error, got {:?}", other),
+        }
+    }
+
+    /// A `CohortResolver` test double that treats a fixed set of cohort ids as
+    /// "matched", independent of the person's properties.
+    struct FakeCohortResolver {
+        matching_cohort_ids: HashSet<CohortId>,
+    }
+
+    impl CohortResolver for FakeCohortResolver {
+        fn resolve_cohort(
+            &self,
+            cohort_id: CohortId,
+            _props: &HashMap<String, serde_json::Value>,
+        ) -> Result<bool, FlagError> {
+            Ok(self.matching_cohort_ids.contains(&cohort_id))
+        }
+    }
+
+    fn property_filter(
+        key: &str,
+        value: serde_json::Value,
+        operator: OperatorType,
+        prop_type: &str,
+        negation: Option<bool>,
+    ) -> PropertyFilter {
+        PropertyFilter {
+            key: key.to_string(),
+            value,
+            operator: Some(operator),
+            prop_type: prop_type.to_string(),
+            group_type_index: None,
+            negation,
+        }
+    }
+
+    #[test]
+    fn test_matches_icontains_and_gte() {
+        let cohort_property = InnerCohortProperty {
+            prop_type: CohortPropertyType::AND,
+            values: vec![CohortValues {
+                prop_type: "AND".to_string(),
+                values: vec![
+                    property_filter(
+                        "email",
+                        json!("user@posthog.com"),
+                        OperatorType::IContains,
+                        "person",
+                        None,
+                    ),
+                    property_filter("age", json!(30), OperatorType::Gte, "person", None),
+                ],
+            }],
+        };
+
+        let mut props = HashMap::new();
+        props.insert("email".to_string(), json!("USER@posthog.com"));
+        props.insert("age".to_string(), json!(30));
+
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: HashSet::new(),
+        };
+
+        assert!(cohort_property.matches(&props, &resolver).unwrap());
+    }
+
+    #[test]
+    fn test_matches_negation_flips_leaf_result() {
+        let cohort_property = InnerCohortProperty {
+            prop_type: CohortPropertyType::AND,
+            values: vec![CohortValues {
+                prop_type: "AND".to_string(),
+                values: vec![property_filter(
+                    "country",
+                    json!(["USA"]),
+                    OperatorType::Exact,
+                    "person",
+                    Some(true),
+                )],
+            }],
+        };
+
+        let mut props = HashMap::new();
+        props.insert("country".to_string(), json!("USA"));
+
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: HashSet::new(),
+        };
+
+        // The filter matches "USA", but negation flips it to false.
+        assert!(!cohort_property.matches(&props, &resolver).unwrap());
+    }
+
+    #[test]
+    fn test_matches_resolves_nested_cohort_reference() {
+        let cohort_property = InnerCohortProperty {
+            prop_type: CohortPropertyType::OR,
+            values: vec![CohortValues {
+                prop_type: "OR".to_string(),
+                values: vec![property_filter(
+                    "id",
+                    json!(42),
+                    OperatorType::Exact,
+                    "cohort",
+                    None,
+                )],
+            }],
+        };
+
+        let props = HashMap::new();
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: [42].into_iter().collect(),
+        };
+
+        assert!(cohort_property.matches(&props, &resolver).unwrap());
+    }
+
+    #[test]
+    fn test_cohort_matches_arbitrarily_nested_groups() {
+        let cohort = Cohort {
+            id: 1,
+            name: Some("Nested Cohort".to_string()),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: Some(json!({
+                "properties": {
+                    "type": "AND",
+                    "values": [
+                        {
+                            "type": "OR",
+                            "values": [
+                                {"key": "id", "type": "cohort", "value": 999}
+                            ]
+                        },
+                        {"key": "email", "type": "person", "value": "@posthog.com", "operator": "icontains"}
+                    ]
+                }
+            })),
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!({}),
+            created_by_id: None,
+        };
+
+        let mut props = HashMap::new();
+        props.insert("email".to_string(), json!("user@posthog.com"));
+
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: [999].into_iter().collect(),
+        };
+
+        assert!(cohort.matches(&props, &resolver).unwrap());
+    }
+
+    #[test]
+    fn test_matches_property_date_only_value() {
+        let filter = property_filter(
+            "signup_date",
+            json!("2024-06-01"),
+            OperatorType::IsDateBefore,
+            "person",
+            None,
+        );
+
+        let mut props = HashMap::new();
+        props.insert("signup_date".to_string(), json!("2024-01-01"));
+
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: HashSet::new(),
+        };
+
+        assert!(InnerCohortProperty::matches_filter(&filter, &props, &resolver).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_evaluate_membership_short_circuits_static_cohort() {
+        let reader = setup_pg_reader_client(None).await;
+        let writer = setup_pg_writer_client(None).await;
+
+        let team = insert_new_team_in_pg(reader.clone(), None)
+            .await
+            .expect("Failed to insert team");
+
+        let static_cohort = insert_cohort_for_team_in_pg(
+            writer.clone(),
+            team.id,
+            Some("Static Cohort".to_string()),
+            json!({}),
+            true,
+        )
+        .await
+        .expect("Failed to insert static cohort");
+
+        let member_person_id = 1;
+
+        let mut conn = writer
+            .get_connection()
+            .await
+            .expect("Failed to get connection");
+        sqlx::query("INSERT INTO posthog_cohortpeople (cohort_id, person_id) VALUES ($1, $2)")
+            .bind(static_cohort.id)
+            .bind(member_person_id)
+            .execute(&mut *conn)
+            .await
+            .expect("Failed to insert static cohort membership");
+        drop(conn);
+
+        // A non-empty filter tree that the empty `props` below can't satisfy:
+        // if `evaluate_membership` didn't short-circuit on `is_static`, this
+        // would evaluate to `false`.
+        let mut cohorts = Cohort::list_from_pg(reader.clone(), team.project_id)
+            .await
+            .expect("Failed to fetch cohorts");
+        let cohort = cohorts
+            .iter_mut()
+            .find(|c| c.id == static_cohort.id)
+            .expect("Failed to find static cohort");
+        cohort.filters = Some(
+            json!({"properties": {"type": "AND", "values": [{"type": "AND", "values": [{"key": "email", "type": "person", "value": "nobody@example.com", "operator": "exact"}]}]}}),
+        );
+
+        let props = HashMap::new();
+        let resolver = FakeCohortResolver {
+            matching_cohort_ids: HashSet::new(),
+        };
+
+        let is_member = cohort
+            .evaluate_membership(reader, member_person_id, &props, &resolver)
+            .await
+            .expect("Failed to evaluate membership");
+
+        assert!(is_member);
+    }
+
+    /// A `CohortStore` test double that counts how many times `list_from_pg`
+    /// actually executed, so tests can assert `CachingCohortStore` memoizes.
+    struct CountingCohortStore {
+        cohorts: Vec<Cohort>,
+        list_calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait]
+    impl CohortStore for CountingCohortStore {
+        async fn list_from_pg(&self, _project_id: i64) -> Result<Vec<Cohort>, FlagError> {
+            self.list_calls
+                .fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(self.cohorts.clone())
+        }
+
+        async fn is_person_in_static_cohort(
+            &self,
+            _cohort_id: CohortId,
+            _person_id: i64,
+        ) -> Result<bool, FlagError> {
+            Ok(false)
+        }
+
+        async fn get_static_cohort_members(
+            &self,
+            _cohort_id: CohortId,
+            _person_ids: &[i64],
+        ) -> Result<HashSet<i64>, FlagError> {
+            Ok(HashSet::new())
+        }
+    }
+
+    fn empty_cohort(id: CohortId) -> Cohort {
+        Cohort {
+            id,
+            name: Some(format!("Cohort {}", id)),
+            description: None,
+            team_id: 1,
+            deleted: false,
+            filters: None,
+            query: None,
+            version: None,
+            pending_version: None,
+            count: None,
+            is_calculating: false,
+            is_static: false,
+            errors_calculating: 0,
+            groups: json!({}),
+            created_by_id: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_caching_cohort_store_memoizes_within_ttl() {
+        let inner = Arc::new(CountingCohortStore {
+            cohorts: vec![empty_cohort(1)],
+            list_calls: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let caching_store = CachingCohortStore::new(inner.clone(), Duration::from_secs(60));
+
+        caching_store.list_from_pg(1).await.unwrap();
+        caching_store.list_from_pg(1).await.unwrap();
+
+        assert_eq!(
+            inner.list_calls.load(std::sync::atomic::Ordering::SeqCst),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn test_caching_cohort_store_refetches_after_ttl_expires() {
+        let inner = Arc::new(CountingCohortStore {
+            cohorts: vec![empty_cohort(1)],
+            list_calls: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let caching_store = CachingCohortStore::new(inner.clone(), Duration::from_millis(10));
+
+        caching_store.list_from_pg(1).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(25)).await;
+        caching_store.list_from_pg(1).await.unwrap();
+
+        assert_eq!(
+            inner.list_calls.load(std::sync::atomic::Ordering::SeqCst),
+            2
+        );
+    }
+
+    #[tokio::test]
+    async fn test_caching_cohort_store_computes_dependency_order() {
+        let inner = Arc::new(CountingCohortStore {
+            cohorts: vec![empty_cohort(1), empty_cohort(2)],
+            list_calls: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let caching_store = CachingCohortStore::new(inner, Duration::from_secs(60));
+
+        let (cohorts, order) = caching_store.list_with_dependency_order(1).await.unwrap();
+
+        assert_eq!(cohorts.len(), 2);
+        assert_eq!(order.len(), 2);
+    }
+
+    fn cyclic_cohort_pair(first: CohortId, second: CohortId) -> (Cohort, Cohort) {
+        let mut cohort_a = empty_cohort(first);
+        cohort_a.filters = Some(
+            json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": second, "negation": false}]}]}}),
+        );
+        let mut cohort_b = empty_cohort(second);
+        cohort_b.filters = Some(
+            json!({"properties": {"type": "OR", "values": [{"type": "OR", "values": [{"key": "id", "type": "cohort", "value": first, "negation": false}]}]}}),
+        );
+        (cohort_a, cohort_b)
+    }
+
+    #[tokio::test]
+    async fn test_caching_cohort_store_list_from_pg_succeeds_despite_cyclic_cohort() {
+        let (cohort_a, cohort_b) = cyclic_cohort_pair(1, 2);
+        let inner = Arc::new(CountingCohortStore {
+            cohorts: vec![cohort_a, cohort_b],
+            list_calls: std::sync::atomic::AtomicUsize::new(0),
+        });
+        let caching_store = CachingCohortStore::new(inner, Duration::from_secs(60));
+
+        // A cyclic cohort must not break the plain list contract, matching
+        // `PgCohortStore::list_from_pg`, even though the dependency graph for
+        // the same project can't be built.
+        let cohorts = caching_store.list_from_pg(1).await.unwrap();
+        assert_eq!(cohorts.len(), 2);
+
+        let result = caching_store.list_with_dependency_order(1).await;
+        assert!(matches!(
+            result,
+            Err(FlagError::CohortDependencyCycle { .. })
+        ));
+    }
 }